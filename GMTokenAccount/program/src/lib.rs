@@ -10,8 +10,11 @@ use solana_program::{
     program_pack::{IsInitialized, Pack},
 };
 use solana_program::program::{invoke, invoke_signed};
+use solana_program::program_option::COption;
 use solana_program::system_instruction;
-use spl_token::state::{Mint, Account};
+use spl_token::instruction::MAX_SIGNERS;
+use spl_token::state::{Mint, Account, AccountState, Multisig};
+use thiserror::Error;
 
 // Define a struct to represent the token
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -21,6 +24,28 @@ pub struct TokenAccount {
     pub amount: u64,
 }
 
+// Errors specific to this program, convertible into a ProgramError
+// so instruction handlers can keep using the `?` operator.
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GmTokenError {
+    #[error("amount overflowed a u64")]
+    Overflow,
+    #[error("account is frozen")]
+    AccountFrozen,
+    #[error("mint account is not a valid, initialized mint")]
+    InvalidMint,
+    #[error("token account belongs to a different mint than expected")]
+    MintMismatch,
+    #[error("account is not a native SOL wrapper")]
+    NotNativeMint,
+}
+
+impl From<GmTokenError> for ProgramError {
+    fn from(e: GmTokenError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
 // Entry point of the program
 entrypoint!(process_instruction);
 
@@ -39,6 +64,13 @@ fn process_instruction(
         1 => create_token_account(program_id, accounts_iter, rest, rent, account),
         2 => mint_tokens(program_id, accounts_iter, rest, account),
         3 => transfer_tokens(program_id, accounts_iter, rest, account),
+        4 => freeze_account(program_id, accounts_iter, account),
+        5 => thaw_account(program_id, accounts_iter, account),
+        6 => approve(program_id, accounts_iter, rest, account),
+        7 => revoke(program_id, accounts_iter, account),
+        8 => burn_tokens(program_id, accounts_iter, rest, account),
+        9 => initialize_multisig(program_id, accounts_iter, rest, rent, account),
+        10 => sync_native(program_id, accounts_iter, account),
         _ => Err(ProgramError::InvalidInstructionData),
     }
 }
@@ -65,9 +97,12 @@ fn create_token(
     let mint_authority = next_account_info(accounts)?;
     let freeze_authority = next_account_info(accounts)?;
 
+    let decimals = *rest.first().ok_or(ProgramError::InvalidInstructionData)?;
+
     let mut mint_info = Mint::unpack_unchecked(&mint_account.data.borrow())?;
     mint_info.mint_authority = COption::Some(*mint_authority.key);
     mint_info.freeze_authority = COption::Some(*freeze_authority.key);
+    mint_info.decimals = decimals;
     mint_info.is_initialized = true;
 
     Mint::pack(mint_info, &mut mint_account.data.borrow_mut())?;
@@ -82,6 +117,7 @@ fn create_token_account(
     account: &AccountInfo,
 ) -> ProgramResult {
     let token_account = next_account_info(accounts)?;
+    let mint_account = next_account_info(accounts)?;
     let owner_account = next_account_info(accounts)?;
 
     let account_data_len = Account::LEN;
@@ -94,9 +130,21 @@ fn create_token_account(
         return Err(ProgramError::InsufficientFunds);
     }
 
+    let mint_info = Mint::unpack(&mint_account.data.borrow())
+        .map_err(|_| GmTokenError::InvalidMint)?;
+
     let mut token_info = Account::unpack_unchecked(&token_account.data.borrow())?;
     token_info.owner = *owner_account.key;
-    token_info.is_initialized = true;
+    token_info.mint = *mint_account.key;
+    token_info.state = AccountState::Initialized;
+
+    if *mint_account.key == spl_token::native_mint::id() {
+        token_info.is_native = COption::Some(rent_exempt_balance);
+        token_info.amount = token_account
+            .lamports()
+            .checked_sub(rent_exempt_balance)
+            .ok_or(ProgramError::InsufficientFunds)?;
+    }
 
     Account::pack(token_info, &mut token_account.data.borrow_mut())?;
     Ok(())
@@ -112,17 +160,32 @@ fn mint_tokens(
     let token_account = next_account_info(accounts)?;
     let mint_authority = next_account_info(accounts)?;
 
-    let amount = u64::from_le_bytes(rest.try_into().unwrap());
+    let amount = parse_amount(rest)?;
 
     let mut mint_info = Mint::unpack(&mint_account.data.borrow())?;
     let mut token_info = Account::unpack(&token_account.data.borrow())?;
 
-    if mint_info.mint_authority != COption::Some(*mint_authority.key) {
-        return Err(ProgramError::IncorrectAuthority);
+    check_authority(&mint_info.mint_authority, mint_authority, accounts)?;
+
+    if token_info.mint != *mint_account.key {
+        return Err(GmTokenError::MintMismatch.into());
+    }
+
+    if token_info.is_frozen() {
+        return Err(GmTokenError::AccountFrozen.into());
     }
 
-    token_info.amount += amount;
+    token_info.amount = token_info
+        .amount
+        .checked_add(amount)
+        .ok_or(GmTokenError::Overflow)?;
+    mint_info.supply = mint_info
+        .supply
+        .checked_add(amount)
+        .ok_or(GmTokenError::Overflow)?;
+
     Account::pack(token_info, &mut token_account.data.borrow_mut())?;
+    Mint::pack(mint_info, &mut mint_account.data.borrow_mut())?;
     Ok(())
 }
 
@@ -134,25 +197,329 @@ fn transfer_tokens(
 ) -> ProgramResult {
     let source_account = next_account_info(accounts)?;
     let destination_account = next_account_info(accounts)?;
-    let owner_account = next_account_info(accounts)?;
+    let authority_account = next_account_info(accounts)?;
 
-    let amount = u64::from_le_bytes(rest.try_into().unwrap());
+    let amount = parse_amount(rest)?;
 
     let mut source_info = Account::unpack(&source_account.data.borrow())?;
     let mut destination_info = Account::unpack(&destination_account.data.borrow())?;
 
-    if source_info.owner != *owner_account.key {
-        return Err(ProgramError::IncorrectAuthority);
+    if source_info.mint != destination_info.mint {
+        return Err(GmTokenError::MintMismatch.into());
+    }
+
+    let is_delegate = source_info.delegate == COption::Some(*authority_account.key)
+        && source_info.delegated_amount >= amount;
+
+    if is_delegate {
+        check_authority(&source_info.delegate, authority_account, accounts)?;
+    } else {
+        check_authority(&COption::Some(source_info.owner), authority_account, accounts)?;
     }
 
     if source_info.amount < amount {
         return Err(ProgramError::InsufficientFunds);
     }
 
-    source_info.amount -= amount;
-    destination_info.amount += amount;
+    if source_info.is_frozen() || destination_info.is_frozen() {
+        return Err(GmTokenError::AccountFrozen.into());
+    }
+
+    if is_delegate {
+        source_info.delegated_amount = source_info
+            .delegated_amount
+            .checked_sub(amount)
+            .ok_or(GmTokenError::Overflow)?;
+        if source_info.delegated_amount == 0 {
+            source_info.delegate = COption::None;
+        }
+    }
+
+    source_info.amount = source_info
+        .amount
+        .checked_sub(amount)
+        .ok_or(GmTokenError::Overflow)?;
+    destination_info.amount = destination_info
+        .amount
+        .checked_add(amount)
+        .ok_or(GmTokenError::Overflow)?;
+
+    if source_info.is_native.is_some() {
+        let mut source_lamports = source_account.try_borrow_mut_lamports()?;
+        let mut destination_lamports = destination_account.try_borrow_mut_lamports()?;
+        **source_lamports = source_lamports
+            .checked_sub(amount)
+            .ok_or(GmTokenError::Overflow)?;
+        **destination_lamports = destination_lamports
+            .checked_add(amount)
+            .ok_or(GmTokenError::Overflow)?;
+    }
 
     Account::pack(source_info, &mut source_account.data.borrow_mut())?;
     Account::pack(destination_info, &mut destination_account.data.borrow_mut())?;
     Ok(())
 }
+
+fn sync_native(
+    program_id: &Pubkey,
+    accounts: &mut std::slice::Iter<AccountInfo>,
+    account: &AccountInfo,
+) -> ProgramResult {
+    let token_account = next_account_info(accounts)?;
+
+    let mut token_info = Account::unpack(&token_account.data.borrow())?;
+
+    let rent_exempt_reserve = match token_info.is_native {
+        COption::Some(reserve) => reserve,
+        COption::None => return Err(GmTokenError::NotNativeMint.into()),
+    };
+
+    token_info.amount = token_account
+        .lamports()
+        .checked_sub(rent_exempt_reserve)
+        .ok_or(GmTokenError::Overflow)?;
+
+    Account::pack(token_info, &mut token_account.data.borrow_mut())?;
+    Ok(())
+}
+
+fn approve(
+    program_id: &Pubkey,
+    accounts: &mut std::slice::Iter<AccountInfo>,
+    rest: &[u8],
+    account: &AccountInfo,
+) -> ProgramResult {
+    let token_account = next_account_info(accounts)?;
+    let delegate_account = next_account_info(accounts)?;
+    let owner_account = next_account_info(accounts)?;
+
+    let amount = parse_amount(rest)?;
+
+    let mut token_info = Account::unpack(&token_account.data.borrow())?;
+
+    check_authority(&COption::Some(token_info.owner), owner_account, accounts)?;
+
+    token_info.delegate = COption::Some(*delegate_account.key);
+    token_info.delegated_amount = amount;
+
+    Account::pack(token_info, &mut token_account.data.borrow_mut())?;
+    Ok(())
+}
+
+fn revoke(
+    program_id: &Pubkey,
+    accounts: &mut std::slice::Iter<AccountInfo>,
+    account: &AccountInfo,
+) -> ProgramResult {
+    let token_account = next_account_info(accounts)?;
+    let owner_account = next_account_info(accounts)?;
+
+    let mut token_info = Account::unpack(&token_account.data.borrow())?;
+
+    check_authority(&COption::Some(token_info.owner), owner_account, accounts)?;
+
+    token_info.delegate = COption::None;
+    token_info.delegated_amount = 0;
+
+    Account::pack(token_info, &mut token_account.data.borrow_mut())?;
+    Ok(())
+}
+
+fn burn_tokens(
+    program_id: &Pubkey,
+    accounts: &mut std::slice::Iter<AccountInfo>,
+    rest: &[u8],
+    account: &AccountInfo,
+) -> ProgramResult {
+    let token_account = next_account_info(accounts)?;
+    let mint_account = next_account_info(accounts)?;
+    let authority_account = next_account_info(accounts)?;
+
+    let amount = parse_amount(rest)?;
+
+    let mut token_info = Account::unpack(&token_account.data.borrow())?;
+    let mut mint_info = Mint::unpack(&mint_account.data.borrow())?;
+
+    if token_info.mint != *mint_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let is_delegate = token_info.delegate == COption::Some(*authority_account.key)
+        && token_info.delegated_amount >= amount;
+
+    if is_delegate {
+        check_authority(&token_info.delegate, authority_account, accounts)?;
+    } else {
+        check_authority(&COption::Some(token_info.owner), authority_account, accounts)?;
+    }
+
+    if token_info.amount < amount {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    if token_info.is_frozen() {
+        return Err(GmTokenError::AccountFrozen.into());
+    }
+
+    if is_delegate {
+        token_info.delegated_amount = token_info
+            .delegated_amount
+            .checked_sub(amount)
+            .ok_or(GmTokenError::Overflow)?;
+        if token_info.delegated_amount == 0 {
+            token_info.delegate = COption::None;
+        }
+    }
+
+    token_info.amount = token_info
+        .amount
+        .checked_sub(amount)
+        .ok_or(GmTokenError::Overflow)?;
+    mint_info.supply = mint_info
+        .supply
+        .checked_sub(amount)
+        .ok_or(GmTokenError::Overflow)?;
+
+    Account::pack(token_info, &mut token_account.data.borrow_mut())?;
+    Mint::pack(mint_info, &mut mint_account.data.borrow_mut())?;
+    Ok(())
+}
+
+fn freeze_account(
+    program_id: &Pubkey,
+    accounts: &mut std::slice::Iter<AccountInfo>,
+    account: &AccountInfo,
+) -> ProgramResult {
+    set_account_frozen(accounts, AccountState::Frozen)
+}
+
+fn thaw_account(
+    program_id: &Pubkey,
+    accounts: &mut std::slice::Iter<AccountInfo>,
+    account: &AccountInfo,
+) -> ProgramResult {
+    set_account_frozen(accounts, AccountState::Initialized)
+}
+
+fn set_account_frozen(
+    accounts: &mut std::slice::Iter<AccountInfo>,
+    new_state: AccountState,
+) -> ProgramResult {
+    let token_account = next_account_info(accounts)?;
+    let mint_account = next_account_info(accounts)?;
+    let freeze_authority = next_account_info(accounts)?;
+
+    let mint_info = Mint::unpack(&mint_account.data.borrow())?;
+    let mut token_info = Account::unpack(&token_account.data.borrow())?;
+
+    if token_info.mint != *mint_account.key {
+        return Err(GmTokenError::MintMismatch.into());
+    }
+
+    check_authority(&mint_info.freeze_authority, freeze_authority, accounts)?;
+
+    token_info.state = new_state;
+    Account::pack(token_info, &mut token_account.data.borrow_mut())?;
+    Ok(())
+}
+
+fn initialize_multisig(
+    program_id: &Pubkey,
+    accounts: &mut std::slice::Iter<AccountInfo>,
+    rest: &[u8],
+    rent: &Rent,
+    account: &AccountInfo,
+) -> ProgramResult {
+    let multisig_account = next_account_info(accounts)?;
+
+    let multisig_data_len = Multisig::LEN;
+    if account.data_len() < multisig_data_len {
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+
+    let rent_exempt_balance = rent.minimum_balance(multisig_data_len);
+    if account.lamports() < rent_exempt_balance {
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let existing = Multisig::unpack_unchecked(&multisig_account.data.borrow())?;
+    if existing.is_initialized {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let m = *rest.first().ok_or(ProgramError::InvalidInstructionData)?;
+
+    let mut signers = [Pubkey::default(); MAX_SIGNERS];
+    let mut n: u8 = 0;
+    for signer_info in accounts {
+        if n as usize >= MAX_SIGNERS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        signers[n as usize] = *signer_info.key;
+        n += 1;
+    }
+
+    if n == 0 || m == 0 || m > n {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let multisig = Multisig {
+        m,
+        n,
+        is_initialized: true,
+        signers,
+    };
+
+    Multisig::pack(multisig, &mut multisig_account.data.borrow_mut())?;
+    Ok(())
+}
+
+// Checks that `authority_account` is the `expected` authority and either
+// signed directly, or — when `expected` names a Multisig account — that at
+// least `m` of the accounts following it in the instruction are both signers
+// and members of that multisig's signer set.
+fn check_authority(
+    expected: &COption<Pubkey>,
+    authority_account: &AccountInfo,
+    remaining_signers: &mut std::slice::Iter<AccountInfo>,
+) -> ProgramResult {
+    if *expected != COption::Some(*authority_account.key) {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+
+    if let Ok(multisig) = Multisig::unpack(&authority_account.data.borrow()) {
+        let signer_slots = &multisig.signers[..multisig.n as usize];
+        let mut matched = [false; MAX_SIGNERS];
+        for signer_info in remaining_signers {
+            if !signer_info.is_signer {
+                continue;
+            }
+            if let Some(slot) = signer_slots.iter().position(|key| key == signer_info.key) {
+                matched[slot] = true;
+            }
+        }
+        let valid_signers = matched[..multisig.n as usize]
+            .iter()
+            .filter(|&&m| m)
+            .count() as u8;
+        return if valid_signers >= multisig.m {
+            Ok(())
+        } else {
+            Err(ProgramError::MissingRequiredSignature)
+        };
+    }
+
+    if !authority_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+// Parses a little-endian u64 amount out of instruction data without panicking
+// on malformed/short input.
+fn parse_amount(rest: &[u8]) -> Result<u64, ProgramError> {
+    let bytes: [u8; 8] = rest
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    Ok(u64::from_le_bytes(bytes))
+}